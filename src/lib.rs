@@ -0,0 +1,1148 @@
+//! Asset-squishing as a reusable library.
+//!
+//! The binary is a thin wrapper over this crate; everything substantive — image
+//! rendering, generic compression and video transcoding — is exposed here so it
+//! can be embedded in a build script, a static-site generator or an upload
+//! handler. The image and generic paths operate entirely in memory (`&[u8]` in,
+//! structured [`RenderedAsset`]s out) so callers can plug in their own I/O
+//! backend; video transcoding remains path-based because libavformat needs
+//! seekable files.
+
+use std::{
+    ffi::{OsStr, OsString},
+    fmt::{Debug, Display},
+    io::{Error as IoError, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use brotli::CompressorReader as BrCompressorReader;
+use flate2::{write::DeflateEncoder, Compression as FlateCompression, GzBuilder};
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder},
+    DynamicImage, EncodableLayout, ImageError,
+};
+use webp::{Encoder as WebPEncoder, WebPEncodingError};
+
+const DEFAULT_ZSTD_LEVEL: i32 = 7;
+const DEFAULT_BROTLI_LEVEL: u32 = 5;
+const DEFAULT_GZIP_LEVEL: u32 = 6;
+const DEFAULT_DEFLATE_LEVEL: u32 = DEFAULT_GZIP_LEVEL;
+
+const DEFAULT_WEBP_COMPRESSION: f32 = 80.0;
+
+const SMALL_IMAGE_PIXELS: u32 = 256;
+const MEDIUM_IMAGE_PIXELS: u32 = 512;
+const LARGE_IMAGE_PIXELS: u32 = 1024;
+
+const DEFAULT_VIDEO_CRF: i64 = 30;
+const DEFAULT_VIDEO_AUDIO_BITRATE: i64 = 128_000;
+const DEFAULT_VIDEO_AV1: bool = false;
+
+const DEFAULT_OXIPNG_LEVEL: u8 = 2;
+
+/// Height-capped rendition tiers, mirroring the image thumbnail tiers.
+pub const VIDEO_HEIGHTS: [(&str, u32); 3] = [("-480p", 480), ("-720p", 720), ("-1080p", 1080)];
+
+/// How a given file extension is handled by the pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetKind {
+    /// A raster image that gets re-rendered into every output format.
+    Image,
+    /// A video that gets transcoded into web-streaming variants.
+    Video,
+    /// Any other file, which gets precompressed losslessly.
+    Generic,
+    /// An already-compressed sibling we leave untouched.
+    Precompressed,
+}
+
+impl AssetKind {
+    /// Classify a file by its extension.
+    #[must_use]
+    pub fn from_extension(ext: &OsStr) -> Self {
+        match ext.as_encoded_bytes() {
+            b"png" | b"jpg" | b"jpeg" | b"bmp" | b"avif" | b"webp" => Self::Image,
+            b"mp4" | b"mov" | b"mkv" | b"avi" | b"webm" | b"m4v" => Self::Video,
+            b"br" | b"gz" | b"zst" | b"zz" => Self::Precompressed,
+            _ => Self::Generic,
+        }
+    }
+}
+
+/// The input extensions this crate recognises and transforms.
+#[must_use]
+pub fn supported_extensions() -> &'static [&'static str] {
+    &[
+        "png", "jpg", "jpeg", "bmp", "avif", "webp", "mp4", "mov", "mkv", "avi", "webm", "m4v",
+    ]
+}
+
+/// A single rendered output: what it is, and its bytes. Callers decide where
+/// (and whether) to persist it.
+pub struct RenderedAsset {
+    /// Name suffix for this tier (`""` for the full-size rendition, `-small`,
+    /// `-medium` or `-large` for the image thumbnail tiers).
+    pub suffix: &'static str,
+    /// Which format/compression produced these bytes.
+    pub variant: Variant,
+    /// The encoded payload.
+    pub bytes: Vec<u8>,
+}
+
+/// The formats and compressions a [`RenderedAsset`] can carry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Avif,
+    Jpeg,
+    Png,
+    WebP,
+    Brotli,
+    Gzip,
+    Zstd,
+    Deflate,
+    /// A byte-for-byte copy of the original input.
+    Identity,
+}
+
+impl Variant {
+    /// The file extension for this variant, or `None` for an identity copy
+    /// (which keeps the original name).
+    #[must_use]
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Avif => Some("avif"),
+            Self::Jpeg => Some("jpeg"),
+            Self::Png => Some("png"),
+            Self::WebP => Some("webp"),
+            Self::Brotli => Some("br"),
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+            Self::Deflate => Some("zz"),
+            Self::Identity => None,
+        }
+    }
+
+    /// A stable label for manifests and logging.
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Avif => "avif",
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::WebP => "webp",
+            Self::Brotli => "br",
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+            Self::Deflate => "zz",
+            Self::Identity => "identity",
+        }
+    }
+
+    /// Whether this variant's extension is appended to the full name (a
+    /// precompressed sibling like `foo.png.br`) rather than replacing it.
+    #[must_use]
+    pub fn appends(self) -> bool {
+        matches!(self, Self::Brotli | Self::Gzip | Self::Zstd | Self::Deflate)
+    }
+}
+
+/// Dispatch an in-memory input to the right transform. Video is not handled
+/// here because it requires seekable file I/O; use [`transcode_video`] and
+/// [`extract_poster_frame`] for that.
+pub fn process_entry(config: &Config, kind: AssetKind, bytes: &[u8]) -> Result<Vec<RenderedAsset>, Error> {
+    match kind {
+        AssetKind::Image => image_compress(config, bytes),
+        AssetKind::Generic => generic_compress(config, bytes),
+        AssetKind::Video | AssetKind::Precompressed => Ok(Vec::new()),
+    }
+}
+
+/// Render an image (and, unless disabled, its thumbnail tiers) into every image
+/// output format.
+pub fn image_compress(config: &Config, bytes: &[u8]) -> Result<Vec<RenderedAsset>, Error> {
+    if config.no_compress_images {
+        return Ok(Vec::new());
+    }
+
+    let image = image::load_from_memory(bytes)?;
+    let mut assets = Vec::new();
+
+    if !config.no_resize_images {
+        let tiers = [
+            ("-small", SMALL_IMAGE_PIXELS),
+            ("-medium", MEDIUM_IMAGE_PIXELS),
+            ("-large", LARGE_IMAGE_PIXELS),
+        ];
+        for (suffix, pixels) in tiers {
+            let thumb = image.thumbnail(pixels, pixels);
+            for (variant, bytes) in render_image(config, &thumb)? {
+                assets.push(RenderedAsset { suffix, variant, bytes });
+            }
+        }
+    }
+
+    for (variant, bytes) in render_image(config, &image)? {
+        assets.push(RenderedAsset { suffix: "", variant, bytes });
+    }
+
+    Ok(assets)
+}
+
+/// Render a single image into AVIF, JPEG, (oxipng-optimized) PNG and WebP.
+pub fn dynamic_render(config: &Config, image: &DynamicImage) -> Result<Vec<RenderedAsset>, Error> {
+    Ok(render_image(config, image)?
+        .into_iter()
+        .map(|(variant, bytes)| RenderedAsset { suffix: "", variant, bytes })
+        .collect())
+}
+
+fn render_image(config: &Config, image: &DynamicImage) -> Result<Vec<(Variant, Vec<u8>)>, Error> {
+    let mut out = Vec::with_capacity(4);
+
+    let mut avif = Vec::new();
+    image.write_with_encoder(AvifEncoder::new(&mut avif))?;
+    out.push((Variant::Avif, avif));
+
+    let mut jpeg = Vec::new();
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new(&mut jpeg))?;
+    out.push((Variant::Jpeg, jpeg));
+
+    let mut png = Vec::new();
+    image.write_with_encoder(PngEncoder::new(&mut png))?;
+    let png = config.oxipng.optimize(png)?;
+    out.push((Variant::Png, png));
+
+    let rgba = image.to_rgba8();
+    let webp_encoder = WebPEncoder::from_rgba(rgba.as_bytes(), rgba.width(), rgba.height());
+    let webp = webp_encoder.encode_advanced(&config.webp.build()?)?;
+    out.push((Variant::WebP, webp.to_vec()));
+
+    Ok(out)
+}
+
+/// Precompress an arbitrary byte blob into brotli/gzip/zstd/deflate siblings
+/// plus an identity copy.
+pub fn generic_compress(config: &Config, bytes: &[u8]) -> Result<Vec<RenderedAsset>, Error> {
+    let mut assets = Vec::with_capacity(5);
+
+    let mut br_out = Vec::new();
+    let mut br = BrCompressorReader::new(bytes, 4096, config.brotli, 20);
+    std::io::copy(&mut br, &mut br_out)?;
+    assets.push(RenderedAsset { suffix: "", variant: Variant::Brotli, bytes: br_out });
+
+    let mut gz = GzBuilder::new().write(Vec::new(), FlateCompression::new(config.gzip));
+    gz.write_all(bytes)?;
+    assets.push(RenderedAsset { suffix: "", variant: Variant::Gzip, bytes: gz.finish()? });
+
+    let zst = zstd::stream::encode_all(bytes, config.zstd)?;
+    assets.push(RenderedAsset { suffix: "", variant: Variant::Zstd, bytes: zst });
+
+    let mut zz = DeflateEncoder::new(Vec::new(), FlateCompression::new(config.deflate));
+    zz.write_all(bytes)?;
+    assets.push(RenderedAsset { suffix: "", variant: Variant::Deflate, bytes: zz.finish()? });
+
+    assets.push(RenderedAsset { suffix: "", variant: Variant::Identity, bytes: bytes.to_vec() });
+
+    Ok(assets)
+}
+
+/// The web-streaming variants a source video can be transcoded into; see
+/// [`video_variants`] for which of them a configuration emits.
+#[derive(Clone, Copy)]
+pub enum VideoVariant {
+    /// H.264 video + AAC audio in an MP4 container, for broad compatibility.
+    H264Mp4,
+    /// VP9 video + Opus audio in a WebM container.
+    Vp9Webm,
+    /// AV1 video + Opus audio in a WebM container, for modern browsers.
+    Av1Webm,
+}
+
+impl VideoVariant {
+    /// The libavcodec encoder name for the video stream of this variant.
+    #[must_use]
+    pub fn video_encoder(self) -> &'static str {
+        match self {
+            Self::H264Mp4 => "libx264",
+            Self::Vp9Webm => "libvpx-vp9",
+            Self::Av1Webm => "libaom-av1",
+        }
+    }
+
+    /// The libavcodec encoder name for the audio stream of this variant.
+    #[must_use]
+    pub fn audio_encoder(self) -> &'static str {
+        match self {
+            Self::H264Mp4 => "aac",
+            Self::Vp9Webm | Self::Av1Webm => "libopus",
+        }
+    }
+
+    /// The output file extension for this variant. The two WebM renditions are
+    /// codec-qualified (`vp9.webm` / `av1.webm`) so they land in distinct files;
+    /// the trailing container segment still selects the muxer.
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::H264Mp4 => "mp4",
+            Self::Vp9Webm => "vp9.webm",
+            Self::Av1Webm => "av1.webm",
+        }
+    }
+
+    /// A stable label for manifests and logging.
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::H264Mp4 => "h264-mp4",
+            Self::Vp9Webm => "vp9-webm",
+            Self::Av1Webm => "av1-webm",
+        }
+    }
+}
+
+/// The video variants to emit for a given configuration.
+#[must_use]
+pub fn video_variants(config: &Config) -> Vec<VideoVariant> {
+    let mut variants = vec![VideoVariant::H264Mp4, VideoVariant::Vp9Webm];
+    if config.video.av1 {
+        variants.push(VideoVariant::Av1Webm);
+    }
+    variants
+}
+
+/// Transcode a single input into one web-streaming variant, optionally capping
+/// the output height (preserving aspect ratio) to `max_height`.
+pub fn transcode_video(
+    input: &Path,
+    output: &Path,
+    variant: VideoVariant,
+    max_height: Option<u32>,
+    config: &VideoConfig,
+) -> Result<(), Error> {
+    use ffmpeg_next::{codec, encoder, format, frame, media, software::scaling, Dictionary};
+
+    let mut ictx = format::input(&input)?;
+    let mut octx = format::output(&output)?;
+
+    let input_stream = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(Error::NoVideoStream)?;
+    let input_index = input_stream.index();
+    // The stream time base is the reliable reference; `decoder.time_base()` is
+    // frequently unset. We configure the encoder with it and stamp/rescale
+    // everything against it.
+    let encoder_time_base = input_stream.time_base();
+
+    let decoder_ctx = codec::context::Context::from_parameters(input_stream.parameters())?;
+    let mut decoder = decoder_ctx.decoder().video()?;
+
+    let encoder_codec = encoder::find_by_name(variant.video_encoder())
+        .ok_or(Error::EncoderUnavailable(variant.video_encoder()))?;
+    let mut ost = octx.add_stream(encoder_codec)?;
+    let ost_index = ost.index();
+
+    let mut video_encoder = codec::context::Context::new_with_codec(encoder_codec)
+        .encoder()
+        .video()?;
+
+    let (width, height) = match max_height {
+        Some(cap) if decoder.height() > cap => {
+            let scaled_width = decoder.width() * cap / decoder.height();
+            // Most encoders require even dimensions.
+            ((scaled_width + 1) & !1, cap)
+        }
+        _ => (decoder.width(), decoder.height()),
+    };
+
+    video_encoder.set_width(width);
+    video_encoder.set_height(height);
+    video_encoder.set_format(format::Pixel::YUV420P);
+    video_encoder.set_time_base(encoder_time_base);
+    video_encoder.set_frame_rate(decoder.frame_rate());
+
+    let mut opts = Dictionary::new();
+    opts.set("crf", &config.crf.to_string());
+    let mut video_encoder = video_encoder.open_with(opts)?;
+    ost.set_parameters(&video_encoder);
+
+    let mut scaler = scaling::Context::get(
+        decoder.format(),
+        decoder.width(),
+        decoder.height(),
+        format::Pixel::YUV420P,
+        width,
+        height,
+        scaling::Flags::BILINEAR,
+    )?;
+
+    // Set up audio transcoding now that the video stream is fully configured;
+    // `None` when the source carries no audio stream.
+    let mut audio = AudioTranscoder::new(&ictx, &mut octx, variant, config)?;
+
+    octx.write_header()?;
+
+    let in_time_base = encoder_time_base;
+    let out_time_base = octx.stream(ost_index).unwrap().time_base();
+    if let Some(audio) = audio.as_mut() {
+        audio.bind_output(&octx);
+    }
+
+    let mut receive_and_write =
+        |encoder: &mut encoder::Video, octx: &mut format::context::Output| -> Result<(), Error> {
+            let mut packet = codec::packet::Packet::empty();
+            while encoder.receive_packet(&mut packet).is_ok() {
+                packet.set_stream(ost_index);
+                packet.rescale_ts(in_time_base, out_time_base);
+                packet.write_interleaved(octx)?;
+            }
+            Ok(())
+        };
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() == input_index {
+            decoder.send_packet(&packet)?;
+            let mut decoded = frame::Video::empty();
+            while decoder.receive_frame(&mut decoded).is_ok() {
+                let mut scaled = frame::Video::empty();
+                scaler.run(&decoded, &mut scaled)?;
+                scaled.set_pts(decoded.timestamp());
+                video_encoder.send_frame(&scaled)?;
+                receive_and_write(&mut video_encoder, &mut octx)?;
+            }
+        } else if let Some(audio) = audio.as_mut() {
+            if stream.index() == audio.input_index() {
+                audio.send_packet(&packet, &mut octx)?;
+            }
+        }
+    }
+
+    decoder.send_eof()?;
+    let mut decoded = frame::Video::empty();
+    while decoder.receive_frame(&mut decoded).is_ok() {
+        let mut scaled = frame::Video::empty();
+        scaler.run(&decoded, &mut scaled)?;
+        scaled.set_pts(decoded.timestamp());
+        video_encoder.send_frame(&scaled)?;
+        receive_and_write(&mut video_encoder, &mut octx)?;
+    }
+
+    video_encoder.send_eof()?;
+    receive_and_write(&mut video_encoder, &mut octx)?;
+
+    if let Some(audio) = audio.as_mut() {
+        audio.flush(&mut octx)?;
+    }
+
+    octx.write_trailer()?;
+
+    Ok(())
+}
+
+/// The pixel height of a video's primary stream, used to decide which
+/// height-capped renditions are worth emitting (there is no point upscaling a
+/// source that is already shorter than a tier's cap).
+pub fn video_height(input: &Path) -> Result<u32, Error> {
+    use ffmpeg_next::{codec, format, media};
+
+    let ictx = format::input(&input)?;
+    let input_stream = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(Error::NoVideoStream)?;
+    let decoder = codec::context::Context::from_parameters(input_stream.parameters())?
+        .decoder()
+        .video()?;
+    Ok(decoder.height())
+}
+
+/// Decode, resample and re-encode the source audio stream into `variant`'s
+/// audio codec (AAC for MP4, Opus for WebM), muxing it alongside the video. A
+/// filter graph handles sample-format conversion and chunks the stream into the
+/// encoder's frame size; the configured [`VideoConfig::audio_bitrate`] sets the
+/// target bitrate.
+struct AudioTranscoder {
+    input_index: usize,
+    output_index: usize,
+    decoder: ffmpeg_next::decoder::Audio,
+    encoder: ffmpeg_next::encoder::Audio,
+    filter: ffmpeg_next::filter::Graph,
+    encoder_time_base: ffmpeg_next::Rational,
+    output_time_base: ffmpeg_next::Rational,
+}
+
+impl AudioTranscoder {
+    fn new(
+        ictx: &ffmpeg_next::format::context::Input,
+        octx: &mut ffmpeg_next::format::context::Output,
+        variant: VideoVariant,
+        config: &VideoConfig,
+    ) -> Result<Option<Self>, Error> {
+        use ffmpeg_next::{codec, encoder, media, ChannelLayout};
+
+        let Some(input_stream) = ictx.streams().best(media::Type::Audio) else {
+            return Ok(None);
+        };
+        let input_index = input_stream.index();
+
+        let decoder_ctx = codec::context::Context::from_parameters(input_stream.parameters())?;
+        let decoder = decoder_ctx.decoder().audio()?;
+
+        let encoder_codec = encoder::find_by_name(variant.audio_encoder())
+            .ok_or(Error::EncoderUnavailable(variant.audio_encoder()))?;
+        let mut output_stream = octx.add_stream(encoder_codec)?;
+        let output_index = output_stream.index();
+
+        let mut encoder = codec::context::Context::new_with_codec(encoder_codec)
+            .encoder()
+            .audio()?;
+
+        let channel_layout = encoder_codec
+            .audio()?
+            .channel_layouts()
+            .map_or(ChannelLayout::STEREO, |layouts| {
+                layouts.best(decoder.channel_layout().channels())
+            });
+        let sample_format = encoder_codec
+            .audio()?
+            .formats()
+            .expect("audio encoder exposes no sample formats")
+            .next()
+            .expect("audio encoder exposes no sample formats");
+
+        // Some encoders (notably libopus) only accept a fixed set of sample
+        // rates, so pick the supported rate nearest the source and let the
+        // filter graph resample to it; default Opus to 48 kHz when the encoder
+        // advertises no explicit list.
+        let source_rate = decoder.rate() as i32;
+        let rate = encoder_codec
+            .audio()?
+            .rates()
+            .and_then(|rates| rates.min_by_key(|rate| (rate - source_rate).abs()))
+            .unwrap_or(if variant.audio_encoder() == "libopus" {
+                48_000
+            } else {
+                source_rate
+            });
+
+        encoder.set_rate(rate);
+        encoder.set_channel_layout(channel_layout);
+        encoder.set_channels(channel_layout.channels());
+        encoder.set_format(sample_format);
+        encoder.set_bit_rate(config.audio_bitrate as usize);
+        encoder.set_time_base((1, rate));
+
+        let encoder = encoder.open_as(encoder_codec)?;
+        output_stream.set_parameters(&encoder);
+
+        let filter = audio_filter(&decoder, &encoder)?;
+        let encoder_time_base = encoder.time_base();
+
+        Ok(Some(Self {
+            input_index,
+            output_index,
+            decoder,
+            encoder,
+            filter,
+            encoder_time_base,
+            output_time_base: encoder_time_base,
+        }))
+    }
+
+    fn input_index(&self) -> usize {
+        self.input_index
+    }
+
+    /// Record the muxer-assigned output time base, known only after the header
+    /// is written.
+    fn bind_output(&mut self, octx: &ffmpeg_next::format::context::Output) {
+        self.output_time_base = octx.stream(self.output_index).unwrap().time_base();
+    }
+
+    fn send_packet(
+        &mut self,
+        packet: &ffmpeg_next::codec::packet::Packet,
+        octx: &mut ffmpeg_next::format::context::Output,
+    ) -> Result<(), Error> {
+        self.decoder.send_packet(packet)?;
+        self.drain_decoder(octx)
+    }
+
+    fn drain_decoder(
+        &mut self,
+        octx: &mut ffmpeg_next::format::context::Output,
+    ) -> Result<(), Error> {
+        let mut decoded = ffmpeg_next::frame::Audio::empty();
+        while self.decoder.receive_frame(&mut decoded).is_ok() {
+            self.filter.get("in").unwrap().source().add(&decoded)?;
+            self.drain_filter(octx)?;
+        }
+        Ok(())
+    }
+
+    fn drain_filter(
+        &mut self,
+        octx: &mut ffmpeg_next::format::context::Output,
+    ) -> Result<(), Error> {
+        let mut filtered = ffmpeg_next::frame::Audio::empty();
+        while self
+            .filter
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            self.encoder.send_frame(&filtered)?;
+            self.write_packets(octx)?;
+        }
+        Ok(())
+    }
+
+    fn write_packets(
+        &mut self,
+        octx: &mut ffmpeg_next::format::context::Output,
+    ) -> Result<(), Error> {
+        let mut packet = ffmpeg_next::codec::packet::Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(self.output_index);
+            packet.rescale_ts(self.encoder_time_base, self.output_time_base);
+            packet.write_interleaved(octx)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, octx: &mut ffmpeg_next::format::context::Output) -> Result<(), Error> {
+        self.decoder.send_eof()?;
+        self.drain_decoder(octx)?;
+        self.filter.get("in").unwrap().source().flush()?;
+        self.drain_filter(octx)?;
+        self.encoder.send_eof()?;
+        self.write_packets(octx)
+    }
+}
+
+/// Build an `abuffer -> abuffersink` graph that resamples the decoded audio into
+/// the encoder's sample format, rate and channel layout, and (for fixed-frame
+/// codecs) chunks it into the encoder's frame size.
+fn audio_filter(
+    decoder: &ffmpeg_next::decoder::Audio,
+    encoder: &ffmpeg_next::encoder::Audio,
+) -> Result<ffmpeg_next::filter::Graph, Error> {
+    use ffmpeg_next::{codec::capabilities::Capabilities, filter};
+
+    let mut graph = filter::Graph::new();
+    let args = format!(
+        "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+        decoder.time_base(),
+        decoder.rate(),
+        decoder.format().name(),
+        decoder.channel_layout().bits(),
+    );
+    graph.add(&filter::find("abuffer").unwrap(), "in", &args)?;
+    graph.add(&filter::find("abuffersink").unwrap(), "out", "")?;
+    {
+        let mut out = graph.get("out").unwrap();
+        out.set_sample_format(encoder.format());
+        out.set_channel_layout(encoder.channel_layout());
+        out.set_sample_rate(encoder.rate());
+    }
+    graph.output("in", 0)?.input("out", 0)?.parse("anull")?;
+    graph.validate()?;
+
+    if let Some(codec) = encoder.codec() {
+        if !codec
+            .capabilities()
+            .contains(Capabilities::VARIABLE_FRAME_SIZE)
+        {
+            graph
+                .get("out")
+                .unwrap()
+                .sink()
+                .set_frame_size(encoder.frame_size());
+        }
+    }
+    Ok(graph)
+}
+
+/// Decode the first frame of a video and convert it into a [`DynamicImage`]
+/// suitable for the image pipeline.
+pub fn extract_poster_frame(input: &Path) -> Result<DynamicImage, Error> {
+    use ffmpeg_next::{codec, format, frame, media, software::scaling};
+
+    let mut ictx = format::input(&input)?;
+    let input_stream = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(Error::NoVideoStream)?;
+    let input_index = input_stream.index();
+
+    let decoder_ctx = codec::context::Context::from_parameters(input_stream.parameters())?;
+    let mut decoder = decoder_ctx.decoder().video()?;
+
+    let mut scaler = scaling::Context::get(
+        decoder.format(),
+        decoder.width(),
+        decoder.height(),
+        format::Pixel::RGB24,
+        decoder.width(),
+        decoder.height(),
+        scaling::Flags::BILINEAR,
+    )?;
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != input_index {
+            continue;
+        }
+        decoder.send_packet(&packet)?;
+        let mut decoded = frame::Video::empty();
+        if decoder.receive_frame(&mut decoded).is_ok() {
+            let mut rgb = frame::Video::empty();
+            scaler.run(&decoded, &mut rgb)?;
+            // ffmpeg pads each row to an alignment, so the plane stride is
+            // usually wider than `width * 3`; copy row-by-row to drop the pad.
+            let width = rgb.width() as usize;
+            let height = rgb.height() as usize;
+            let stride = rgb.stride(0);
+            let data = rgb.data(0);
+            let mut packed = Vec::with_capacity(width * height * 3);
+            for row in 0..height {
+                let start = row * stride;
+                packed.extend_from_slice(&data[start..start + width * 3]);
+            }
+            let buffer = image::RgbImage::from_raw(rgb.width(), rgb.height(), packed)
+                .ok_or(Error::PosterFrame)?;
+            return Ok(DynamicImage::ImageRgb8(buffer));
+        }
+    }
+
+    Err(Error::NoVideoStream)
+}
+
+/// Append `ext` after the existing file name, e.g. `foo.png` -> `foo.png.br`.
+#[must_use]
+pub fn add_extension(path: PathBuf, ext: impl AsRef<OsStr>) -> PathBuf {
+    let mut os_string: OsString = path.into();
+    os_string.push(".");
+    os_string.push(ext.as_ref());
+    os_string.into()
+}
+
+/// Insert `extra_text` between the file stem and its extension, e.g.
+/// `gen_path("foo.png", "-small")` -> `foo-small.png`.
+pub fn gen_path(path: &Path, extra_text: &str) -> Result<PathBuf, Error> {
+    let old_extension = path.extension().ok_or(Error::NoExtension)?;
+    let old_name = path
+        .with_extension("")
+        .file_name()
+        .ok_or(Error::NoFileName)?
+        .to_owned();
+    let mut new_file_name =
+        OsString::with_capacity(old_name.len() + extra_text.len() + 1 + old_extension.len());
+    new_file_name.push(old_name);
+    new_file_name.push(extra_text);
+    new_file_name.push(".");
+    new_file_name.push(old_extension);
+    Ok(path.with_file_name(new_file_name))
+}
+
+fn cfg_int<T>(name: &str, range: RangeInclusive<T>, default: T) -> T
+where
+    T: FromStr + Display + PartialEq + PartialOrd,
+    T::Err: Debug,
+{
+    let level: T = std::env::var(name)
+        .map(|v| {
+            v.parse()
+                .unwrap_or_else(|_| panic!("{name} must be a valid integer"))
+        })
+        .unwrap_or(default);
+    if !range.contains(&level) {
+        panic!(
+            "{name} must be between {} and {}, inclusive.",
+            range.start(),
+            range.end()
+        );
+    }
+    level
+}
+
+fn cfg_float(name: &str, range: RangeInclusive<f32>, default: f32) -> f32 {
+    let level: f32 = std::env::var(name)
+        .map(|v| {
+            v.parse()
+                .unwrap_or_else(|_| panic!("{name} must be a valid float"))
+        })
+        .unwrap_or(default);
+    if !range.contains(&level) {
+        panic!(
+            "{name} must be between {} and {}, inclusive.",
+            range.start(),
+            range.end()
+        );
+    }
+    level
+}
+
+/// The encoding configuration shared by every transform. Build one with
+/// [`Config::builder`], or take the environment-driven defaults via
+/// [`Config::from_env`].
+#[derive(Clone)]
+pub struct Config {
+    webp: WebPQualityConfig,
+    oxipng: OxipngConfig,
+    video: VideoConfig,
+    brotli: u32,
+    zstd: i32,
+    deflate: u32,
+    gzip: u32,
+    no_resize_images: bool,
+    no_compress_images: bool,
+    no_resize_videos: bool,
+}
+
+impl Config {
+    /// Start building a configuration from the environment-driven defaults.
+    #[must_use]
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Self::from_env(),
+        }
+    }
+
+    /// Build a configuration entirely from env vars and compiled-in defaults.
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self {
+            webp: WebPQualityConfig::default(),
+            oxipng: OxipngConfig::new(),
+            video: VideoConfig::new(),
+            zstd: cfg_int(
+                "ZSTD_LEVEL",
+                zstd::compression_level_range(),
+                DEFAULT_ZSTD_LEVEL,
+            ),
+            brotli: cfg_int("BROTLI_LEVEL", 1..=11, DEFAULT_BROTLI_LEVEL),
+            deflate: cfg_int("DEFLATE_LEVEL", 1..=9, DEFAULT_DEFLATE_LEVEL),
+            gzip: cfg_int("GZIP_LEVEL", 1..=9, DEFAULT_GZIP_LEVEL),
+            no_resize_images: false,
+            no_compress_images: false,
+            no_resize_videos: false,
+        }
+    }
+
+    /// The per-video encoder settings.
+    #[must_use]
+    pub fn video(&self) -> &VideoConfig {
+        &self.video
+    }
+
+    /// Whether height-capped video renditions are suppressed.
+    #[must_use]
+    pub fn no_resize_videos(&self) -> bool {
+        self.no_resize_videos
+    }
+
+    /// The configured gzip compression level (1–9).
+    #[must_use]
+    pub fn gzip(&self) -> u32 {
+        self.gzip
+    }
+
+    /// The configured zstd compression level.
+    #[must_use]
+    pub fn zstd(&self) -> i32 {
+        self.zstd
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Builder for [`Config`], starting from the environment-driven defaults.
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Copy images as-is without rendering alternative formats.
+    #[must_use]
+    pub fn no_compress_images(mut self, value: bool) -> Self {
+        self.config.no_compress_images = value;
+        self
+    }
+
+    /// Suppress the per-image thumbnail tiers.
+    #[must_use]
+    pub fn no_resize_images(mut self, value: bool) -> Self {
+        self.config.no_resize_images = value;
+        self
+    }
+
+    /// Suppress the height-capped video renditions.
+    #[must_use]
+    pub fn no_resize_videos(mut self, value: bool) -> Self {
+        self.config.no_resize_videos = value;
+        self
+    }
+
+    /// Finish building.
+    #[must_use]
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
+
+const OXIPNG_DEFLATE_ZOPFLI_ITERATIONS: u8 = 15;
+
+#[derive(Clone, Copy)]
+struct OxipngConfig {
+    level: u8,
+    zopfli: bool,
+    strip: bool,
+}
+
+impl OxipngConfig {
+    fn new() -> Self {
+        Self {
+            level: cfg_int("OXIPNG_LEVEL", 0..=6, DEFAULT_OXIPNG_LEVEL),
+            zopfli: std::env::var("OXIPNG_ZOPFLI").is_ok_and(|v| v != "false" && v != "0"),
+            strip: std::env::var("OXIPNG_STRIP").map_or(true, |v| v != "false" && v != "0"),
+        }
+    }
+
+    fn optimize(&self, png: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let mut options = oxipng::Options::from_preset(self.level);
+        if self.zopfli {
+            options.deflate = oxipng::Deflaters::Zopfli {
+                iterations: OXIPNG_DEFLATE_ZOPFLI_ITERATIONS
+                    .try_into()
+                    .expect("15 is a valid iteration count"),
+            };
+        }
+        if self.strip {
+            options.strip = oxipng::StripChunks::Safe;
+        }
+        Ok(oxipng::optimize_from_memory(&png, &options)?)
+    }
+}
+
+/// Per-video encoder settings.
+#[derive(Clone, Copy)]
+pub struct VideoConfig {
+    /// Constant rate factor handed to the video encoders; lower is higher quality.
+    pub crf: i64,
+    /// Target audio bitrate, in bits per second.
+    pub audio_bitrate: i64,
+    /// Whether to additionally emit an AV1 WebM rendition.
+    pub av1: bool,
+}
+
+impl VideoConfig {
+    fn new() -> Self {
+        Self {
+            crf: cfg_int("VIDEO_CRF", 0..=63, DEFAULT_VIDEO_CRF),
+            audio_bitrate: cfg_int(
+                "VIDEO_AUDIO_BITRATE",
+                8_000..=512_000,
+                DEFAULT_VIDEO_AUDIO_BITRATE,
+            ),
+            av1: std::env::var("VIDEO_AV1").map_or(DEFAULT_VIDEO_AV1, |v| v != "false" && v != "0"),
+        }
+    }
+}
+
+/// The full set of libwebp advanced encoding knobs we expose, each driven by an
+/// env var and validated up front. Defaults mirror libwebp's own so that an
+/// unconfigured run behaves like the previous `encode_simple` path.
+#[derive(Clone, Copy)]
+struct WebPQualityConfig {
+    lossless: bool,
+    quality: f32,
+    method: i32,
+    sns_strength: i32,
+    filter_strength: i32,
+    filter_sharpness: i32,
+    filter_type: i32,
+    autofilter: i32,
+    segments: i32,
+    alpha_compression: i32,
+    alpha_filtering: i32,
+    alpha_quality: i32,
+    pass: i32,
+    preprocessing: i32,
+    target_size: i32,
+    target_psnr: f32,
+}
+
+impl Default for WebPQualityConfig {
+    fn default() -> Self {
+        let lossless = std::env::var("WEBP_LOSSLESS").is_ok_and(|v| v != "false" && v != "0");
+        let quality = if let Ok(requested_quality) = std::env::var("WEBP_QUALITY") {
+            let requested_quality: f32 = requested_quality
+                .parse()
+                .expect("WEBP_QUALITY must be a float between 0 and 100, inclusive.");
+            if !(0.0..=100.0).contains(&requested_quality) {
+                panic!("Expected WEBP_QUALITY to be a float between 0 and 100, inclusive.");
+            }
+            requested_quality
+        } else {
+            DEFAULT_WEBP_COMPRESSION
+        };
+        Self {
+            lossless,
+            quality,
+            method: cfg_int("WEBP_METHOD", 0..=6, 4),
+            sns_strength: cfg_int("WEBP_SNS_STRENGTH", 0..=100, 50),
+            filter_strength: cfg_int("WEBP_FILTER_STRENGTH", 0..=100, 60),
+            filter_sharpness: cfg_int("WEBP_FILTER_SHARPNESS", 0..=7, 0),
+            filter_type: cfg_int("WEBP_FILTER_TYPE", 0..=1, 1),
+            autofilter: cfg_int("WEBP_AUTOFILTER", 0..=1, 0),
+            segments: cfg_int("WEBP_SEGMENTS", 1..=4, 4),
+            alpha_compression: cfg_int("WEBP_ALPHA_COMPRESSION", 0..=1, 1),
+            alpha_filtering: cfg_int("WEBP_ALPHA_FILTERING", 0..=2, 1),
+            alpha_quality: cfg_int("WEBP_ALPHA_QUALITY", 0..=100, 100),
+            pass: cfg_int("WEBP_PASS", 1..=10, 1),
+            preprocessing: cfg_int("WEBP_PREPROCESSING", 0..=2, 0),
+            target_size: cfg_int("WEBP_TARGET_SIZE", 0..=i32::MAX, 0),
+            target_psnr: cfg_float("WEBP_TARGET_PSNR", 0.0..=99.0, 0.0),
+        }
+    }
+}
+
+impl WebPQualityConfig {
+    /// Build a populated libwebp [`WebPConfig`](webp::WebPConfig) from these settings.
+    fn build(&self) -> Result<webp::WebPConfig, Error> {
+        let mut config = webp::WebPConfig::new().map_err(|()| Error::WebPConfig)?;
+        config.lossless = i32::from(self.lossless);
+        config.quality = self.quality.clamp(0.0, 100.0);
+        config.method = self.method;
+        config.sns_strength = self.sns_strength;
+        config.filter_strength = self.filter_strength;
+        config.filter_sharpness = self.filter_sharpness;
+        config.filter_type = self.filter_type;
+        config.autofilter = self.autofilter;
+        config.segments = self.segments;
+        config.alpha_compression = self.alpha_compression;
+        config.alpha_filtering = self.alpha_filtering;
+        config.alpha_quality = self.alpha_quality;
+        config.pass = self.pass;
+        config.preprocessing = self.preprocessing;
+        config.target_size = self.target_size;
+        config.target_PSNR = self.target_psnr;
+        Ok(config)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] IoError),
+    #[error("Directory walking error: {0}")]
+    Walkdir(#[from] walkdir::Error),
+    #[error("Prefix stripping error")]
+    StripPrefixError(#[from] std::path::StripPrefixError),
+    #[error("Image coding error: {0}")]
+    Image(#[from] ImageError),
+    #[error("Manifest serialization error: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("WebP Encoding error")]
+    WebP(WebPEncodingError),
+    #[error("Could not initialize a libwebp encoder configuration")]
+    WebPConfig,
+    #[error("PNG optimization error: {0}")]
+    Oxipng(#[from] oxipng::PngError),
+    #[error("Archive creation error: {0}")]
+    Archive(std::io::Error),
+    #[error("LZ4 compression error: {0}")]
+    Lz4(#[from] lz4_flex::frame::Error),
+    #[error("Video transcoding error: {0}")]
+    Video(#[from] ffmpeg_next::Error),
+    #[error("Input file contained no decodable video stream")]
+    NoVideoStream,
+    #[error("Required encoder `{0}` is not available in the linked ffmpeg build")]
+    EncoderUnavailable(&'static str),
+    #[error("Decoded poster frame did not fit the expected RGB buffer size")]
+    PosterFrame,
+    #[error("Encountered a file with no extension")]
+    NoExtension,
+    #[error("Encountered a file with no name")]
+    NoFileName,
+    #[error("WebP does not support some dynamic image types: https://docs.rs/webp/0.2.6/src/webp/encoder.rs.html#29-45")]
+    UnimplementedWebPImageFormat,
+}
+
+impl From<WebPEncodingError> for Error {
+    fn from(value: WebPEncodingError) -> Self {
+        Self::WebP(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    #[test]
+    fn classifies_extensions() {
+        assert_eq!(AssetKind::from_extension(OsStr::new("png")), AssetKind::Image);
+        assert_eq!(AssetKind::from_extension(OsStr::new("mp4")), AssetKind::Video);
+        assert_eq!(
+            AssetKind::from_extension(OsStr::new("br")),
+            AssetKind::Precompressed
+        );
+        assert_eq!(
+            AssetKind::from_extension(OsStr::new("txt")),
+            AssetKind::Generic
+        );
+    }
+
+    #[test]
+    fn gen_path_inserts_before_extension() {
+        let path = gen_path(Path::new("a/b/foo.png"), "-small").unwrap();
+        assert_eq!(path, PathBuf::from("a/b/foo-small.png"));
+    }
+
+    #[test]
+    fn gen_path_requires_an_extension() {
+        assert!(matches!(
+            gen_path(Path::new("noext"), "-small"),
+            Err(Error::NoExtension)
+        ));
+    }
+
+    #[test]
+    fn add_extension_appends_a_sibling_suffix() {
+        let path = add_extension(PathBuf::from("foo.png"), "br");
+        assert_eq!(path, PathBuf::from("foo.png.br"));
+    }
+
+    #[test]
+    fn generic_compress_keeps_identity_and_roundtrips() {
+        let config = Config::from_env();
+        let input = b"the quick brown fox jumps over the lazy dog".repeat(16);
+        let assets = generic_compress(&config, &input).unwrap();
+
+        let identity = assets
+            .iter()
+            .find(|asset| asset.variant == Variant::Identity)
+            .expect("identity copy is always emitted");
+        assert_eq!(identity.bytes, input);
+
+        let zstd_asset = assets
+            .iter()
+            .find(|asset| asset.variant == Variant::Zstd)
+            .expect("a zstd variant is always emitted");
+        let decoded = zstd::stream::decode_all(zstd_asset.bytes.as_slice()).unwrap();
+        assert_eq!(decoded, input);
+    }
+}
@@ -1,36 +1,21 @@
 use std::{
-    ffi::{OsStr, OsString},
-    fmt::{Debug, Display},
-    fs::{File, OpenOptions},
-    io::{Error as IoError, Seek, SeekFrom, Write},
-    ops::RangeInclusive,
+    io::Write,
     path::{Path, PathBuf},
     process::ExitCode,
-    str::FromStr,
-    sync::{atomic::AtomicBool, Arc},
+    sync::{atomic::AtomicBool, Arc, Mutex},
     time::Instant,
 };
 
-use brotli::CompressorReader as BrCompressorReader;
-use flate2::{write::DeflateEncoder, Compression as FlateCompression, GzBuilder};
-use image::{
-    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder},
-    DynamicImage, EncodableLayout, ImageError,
+use asset_squisher::{
+    add_extension, dynamic_render, extract_poster_frame, gen_path, generic_compress,
+    image_compress, transcode_video, video_height, video_variants, AssetKind, Config, Error,
+    RenderedAsset, VIDEO_HEIGHTS,
 };
+use flate2::{write::GzEncoder, Compression};
+use lz4_flex::frame::FrameEncoder;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use walkdir::{DirEntry, Error as WalkDirError, WalkDir};
-use webp::{Encoder as WebPEncoder, WebPEncodingError};
-
-const DEFAULT_ZSTD_LEVEL: i32 = 7;
-const DEFAULT_BROTLI_LEVEL: u32 = 5;
-const DEFAULT_GZIP_LEVEL: u32 = 6;
-const DEFAULT_DEFLATE_LEVEL: u32 = DEFAULT_GZIP_LEVEL;
-
-const DEFAULT_WEBP_COMPRESSION: f32 = 80.0;
-
-const SMALL_IMAGE_PIXELS: u32 = 256;
-const MEDIUM_IMAGE_PIXELS: u32 = 512;
-const LARGE_IMAGE_PIXELS: u32 = 1024;
+use sha2::{Digest, Sha256};
+use walkdir::{DirEntry, WalkDir};
 
 #[derive(argh::FromArgs)]
 /// A simple application to compress all web assits in a static file directory.
@@ -47,18 +32,34 @@ struct Arguments {
     /// do you wish to not touch images at all, and copy them as-is?
     #[argh(switch)]
     no_compress_images: bool,
+    /// do you wish to supress the creation of separate height-capped video renditions
+    #[argh(switch)]
+    no_resize_videos: bool,
+    /// do you wish to hash a short content fingerprint into each output filename (e.g. app.9f3c1a2b.png)
+    #[argh(switch)]
+    fingerprint: bool,
+    /// pack the output tree into a tar archive at this path, plus whole-archive .zst/.gz/.lz4 forms
+    #[argh(option)]
+    archive: Option<PathBuf>,
+    /// include the precompressed .br/.gz/.zst/.zz siblings in the archive (excluded by default)
+    #[argh(switch)]
+    archive_include_compressed: bool,
 }
 
 fn main() -> ExitCode {
     let args: Arguments = argh::from_env();
 
-    let config = Config::new(
-        &args.indir,
-        &args.outdir,
-        args.no_resize_images,
-        args.no_compress_images,
-    );
+    ffmpeg_next::init().expect("failed to initialize ffmpeg");
+
+    let config = Config::builder()
+        .no_resize_images(args.no_resize_images)
+        .no_compress_images(args.no_compress_images)
+        .no_resize_videos(args.no_resize_videos)
+        .build();
+
     let failed = Arc::new(AtomicBool::new(false));
+    let manifest: Mutex<Vec<ManifestEntry>> = Mutex::new(Vec::new());
+
     let existing_files: Vec<DirEntry> = WalkDir::new(args.indir.clone())
         .into_iter()
         .filter_map(|v| match v {
@@ -81,16 +82,36 @@ fn main() -> ExitCode {
         let path_display = item.path().display().to_string();
         println!("compressing file {path_display}");
         let start = Instant::now();
-        let processed = process_entry(config.clone(), item);
+        let processed = process_entry(&config, &args, item);
         let end = Instant::now();
         let duration = end.duration_since(start).as_secs_f64();
-        if let Err(e) = processed {
-            failed.store(true, std::sync::atomic::Ordering::Release);
-            eprintln!("failed to process file {path_display}: {e} (took {duration:.2} seconds)",);
-        } else {
-            println!("compressed {path_display} in {duration:.2} seconds");
+        match processed {
+            Ok(entry) => {
+                manifest.lock().expect("manifest mutex poisoned").push(entry);
+                println!("compressed {path_display} in {duration:.2} seconds");
+            }
+            Err(e) => {
+                failed.store(true, std::sync::atomic::Ordering::Release);
+                eprintln!(
+                    "failed to process file {path_display}: {e} (took {duration:.2} seconds)",
+                );
+            }
         }
     });
+
+    let manifest = manifest.into_inner().expect("manifest mutex poisoned");
+    if let Err(e) = write_manifest(&args.outdir, &manifest) {
+        failed.store(true, std::sync::atomic::Ordering::Release);
+        eprintln!("failed to write manifest: {e}");
+    }
+
+    if let Some(archive_path) = &args.archive {
+        if let Err(e) = write_archive(&args, &config, archive_path) {
+            failed.store(true, std::sync::atomic::Ordering::Release);
+            eprintln!("failed to write archive: {e}");
+        }
+    }
+
     if failed.load(std::sync::atomic::Ordering::Acquire) {
         ExitCode::FAILURE
     } else {
@@ -98,259 +119,299 @@ fn main() -> ExitCode {
     }
 }
 
-fn process_entry(config: Config, item: DirEntry) -> Result<(), Error> {
-    let ext = item.path().extension().ok_or(Error::NoExtension)?;
-    match ext.as_encoded_bytes() {
-        b"png" | b"jpg" | b"jpeg" | b"bmp" | b"avif" | b"webp" => image_compress(config, item)?,
-        b"br" | b"gz" | b"zst" | b"zz" => {}
-        _ => generic_compress(config, item)?,
-    }
-    Ok(())
-}
-
-fn generic_compress(config: Config, item: DirEntry) -> Result<(), Error> {
-    let item_path = item.clone().into_path();
-    let output_path = config.out_dir.join(item_path.strip_prefix(config.in_dir)?);
-    let mut initial = OpenOptions::new().read(true).open(&item_path)?;
-
+/// Process one file from the input tree: transform it through the library and
+/// write the results under the output tree, collecting manifest records.
+fn process_entry(config: &Config, args: &Arguments, item: DirEntry) -> Result<ManifestEntry, Error> {
+    let path = item.path();
+    let source = path.strip_prefix(&args.indir)?.to_path_buf();
+    let output_path = args.outdir.join(&source);
     std::fs::create_dir_all(output_path.parent().unwrap_or(output_path.as_ref()))?;
 
-    let mut br_file = create_new_extended(&output_path, "br")?;
-    let mut br = BrCompressorReader::new(&mut initial, 4096, config.brotli, 20);
-    std::io::copy(&mut br, &mut br_file)?;
-    drop(br_file);
-    initial.seek(SeekFrom::Start(0))?;
-
-    let gz_file = create_new_extended(&output_path, "gz")?;
-    let mut gz = GzBuilder::new().write(gz_file, FlateCompression::new(config.gzip));
-    std::io::copy(&mut initial, &mut gz)?;
-    drop(gz);
-    initial.seek(SeekFrom::Start(0))?;
-
-    let zst_file = create_new_extended(&output_path, "zst")?;
-    zstd::stream::copy_encode(&mut initial, zst_file, config.zstd)?;
-    initial.seek(SeekFrom::Start(0))?;
-
-    let zz_file = create_new_extended(&output_path, "zz")?;
-    let mut zz = DeflateEncoder::new(zz_file, FlateCompression::new(config.deflate));
-    std::io::copy(&mut initial, &mut zz)?;
-    drop(zz);
-    initial.seek(SeekFrom::Start(0))?;
+    let ext = path.extension().ok_or(Error::NoExtension)?;
+    let kind = AssetKind::from_extension(ext);
+    let mut variants = Vec::new();
 
-    std::fs::copy(item_path, output_path)?;
+    match kind {
+        AssetKind::Image => {
+            let bytes = std::fs::read(path)?;
+            let assets = image_compress(config, &bytes)?;
+            for asset in assets {
+                write_asset(args, &output_path, &asset, &mut variants)?;
+            }
+            // Preserve the original under its own name unless a rendered variant
+            // already occupies it (i.e. the input extension matches one of the
+            // emitted image formats). Keying on the input kind rather than on
+            // whether a path survived the `--fingerprint` rename keeps the copy
+            // decision identical with and without fingerprinting. In
+            // `no_compress_images` mode nothing is rendered, so the original is
+            // always the only copy.
+            if args.no_compress_images || !image_variant_covers(ext) {
+                std::fs::copy(path, &output_path)?;
+                finalize_variant(args, output_path, "identity", &bytes, &mut variants)?;
+            }
+        }
+        AssetKind::Generic => {
+            let bytes = std::fs::read(path)?;
+            for asset in generic_compress(config, &bytes)? {
+                write_asset(args, &output_path, &asset, &mut variants)?;
+            }
+        }
+        AssetKind::Video => compress_video(config, args, path, &output_path, &mut variants)?,
+        AssetKind::Precompressed => {}
+    }
 
-    Ok(())
+    Ok(ManifestEntry { source, variants })
 }
 
-fn image_compress(config: Config, item: DirEntry) -> Result<(), Error> {
-    let path = item.path();
-    let output_path = config.out_dir.join(path.strip_prefix(config.in_dir)?);
-
-    std::fs::create_dir_all(output_path.parent().unwrap_or(output_path.as_ref()))?;
-
-    if !config.no_compress_images {
-        let image = image::open(path)?;
-
-        if !config.no_resize_images {
-            let small_image = image.thumbnail(SMALL_IMAGE_PIXELS, SMALL_IMAGE_PIXELS);
-            let medium_image = image.thumbnail(MEDIUM_IMAGE_PIXELS, MEDIUM_IMAGE_PIXELS);
-            let large_image = image.thumbnail(LARGE_IMAGE_PIXELS, LARGE_IMAGE_PIXELS);
-            dynamic_render(&config, small_image, &gen_path(&output_path, "-small")?)?;
-            dynamic_render(&config, medium_image, &gen_path(&output_path, "-medium")?)?;
-            dynamic_render(&config, large_image, &gen_path(&output_path, "-large")?)?;
+/// Transcode a source video into every web-streaming rendition, plus an
+/// extracted poster frame run through the image pipeline.
+fn compress_video(
+    config: &Config,
+    args: &Arguments,
+    path: &Path,
+    output_path: &Path,
+    variants: &mut Vec<VariantRecord>,
+) -> Result<(), Error> {
+    let codecs = video_variants(config);
+
+    // Untouched full-resolution renditions, plus the height-capped tiers. Tiers
+    // whose cap is at or above the source height would only upscale, so skip
+    // them rather than emit misleadingly named copies of the original.
+    let mut tiers: Vec<(String, Option<u32>)> = vec![(String::new(), None)];
+    if !config.no_resize_videos() {
+        let source_height = video_height(path)?;
+        for (suffix, height) in VIDEO_HEIGHTS {
+            if height < source_height {
+                tiers.push((suffix.to_owned(), Some(height)));
+            }
         }
+    }
 
-        dynamic_render(&config, image, &output_path)?;
+    for (suffix, height) in &tiers {
+        let tier_path = if suffix.is_empty() {
+            output_path.to_path_buf()
+        } else {
+            gen_path(output_path, suffix)?
+        };
+        for variant in &codecs {
+            let out = tier_path.with_extension(variant.extension());
+            transcode_video(path, &out, *variant, *height, config.video())?;
+            // The rendition is produced by the muxer on disk, so read it back in
+            // full to hash it.
+            let bytes = std::fs::read(&out)?;
+            finalize_variant(args, out, variant.label(), &bytes, variants)?;
+        }
     }
 
-    if !output_path.try_exists()? {
-        std::fs::copy(path, &output_path)?;
+    let poster = extract_poster_frame(path)?;
+    let poster_base = gen_path(output_path, "-poster")?;
+    for asset in dynamic_render(config, &poster)? {
+        write_asset(args, &poster_base, &asset, variants)?;
     }
 
     Ok(())
 }
 
-fn gen_path(path: &Path, extra_text: &str) -> Result<PathBuf, Error> {
-    let old_extension = path.extension().ok_or(Error::NoExtension)?;
-    let old_name = path
-        .with_extension("")
-        .file_name()
-        .ok_or(Error::NoFileName)?
-        .to_owned();
-    let mut new_file_name =
-        OsString::with_capacity(old_name.len() + extra_text.len() + 1 + old_extension.len());
-    new_file_name.push(old_name);
-    new_file_name.push(extra_text);
-    new_file_name.push(".");
-    new_file_name.push(old_extension);
-    Ok(path.with_file_name(new_file_name))
+/// Resolve a rendered asset's output path, write its bytes, and record it.
+fn write_asset(
+    args: &Arguments,
+    base: &Path,
+    asset: &RenderedAsset,
+    variants: &mut Vec<VariantRecord>,
+) -> Result<(), Error> {
+    let tier_base = if asset.suffix.is_empty() {
+        base.to_path_buf()
+    } else {
+        gen_path(base, asset.suffix)?
+    };
+    let path = match asset.variant.extension() {
+        Some(ext) if asset.variant.appends() => add_extension(tier_base, ext),
+        Some(ext) => tier_base.with_extension(ext),
+        None => tier_base,
+    };
+    let mut file = create_file(&path)?;
+    file.write_all(&asset.bytes)?;
+    drop(file);
+    finalize_variant(args, path, asset.variant.label(), &asset.bytes, variants)
 }
 
-fn dynamic_render(config: &Config, image: DynamicImage, output_path: &Path) -> Result<(), Error> {
-    let avif_out = create_file(output_path.with_extension("avif"))?;
-    image.write_with_encoder(AvifEncoder::new(avif_out))?;
-
-    let jpeg_out = create_file(output_path.with_extension("jpeg"))?;
-    let jpeg_quality_dropped_image = image.clone().into_rgb8();
-    jpeg_quality_dropped_image.write_with_encoder(JpegEncoder::new(jpeg_out))?;
-
-    let png_out = create_file(output_path.with_extension("png"))?;
-    image.write_with_encoder(PngEncoder::new(png_out))?;
-
-    let image_rgba = image.into_rgba8();
-    let webp_encoder = WebPEncoder::from_rgba(
-        image_rgba.as_bytes(),
-        image_rgba.width(),
-        image_rgba.height(),
-    );
-    let webp_pixmap = webp_encoder.encode_simple(config.webp.lossless(), config.webp.quality())?;
-    let mut webp_out = create_file(output_path.with_extension("webp"))?;
-    webp_out.write_all(webp_pixmap.as_ref())?;
-    drop(webp_out);
-
-    Ok(())
+/// Whether the image pipeline emits a full-size rendition sharing `ext`, so a
+/// byte-for-byte copy of the original under that name would be redundant.
+fn image_variant_covers(ext: &std::ffi::OsStr) -> bool {
+    matches!(ext.as_encoded_bytes(), b"avif" | b"jpeg" | b"png" | b"webp")
 }
 
-fn create_file(path: impl AsRef<Path>) -> Result<File, IoError> {
-    OpenOptions::new()
+fn create_file(path: impl AsRef<Path>) -> Result<std::fs::File, std::io::Error> {
+    std::fs::OpenOptions::new()
         .write(true)
         .create_new(true)
         .open(path.as_ref())
 }
 
-fn create_new_extended(path: &Path, ext: impl AsRef<OsStr>) -> Result<File, IoError> {
-    let extended = add_extension(path.to_path_buf(), ext);
-    create_file(extended)
+/// A single emitted file, identified by its content hash so a server can serve
+/// it with correct `ETag`/`Integrity` headers without re-stat'ing the tree.
+#[derive(serde::Serialize)]
+struct VariantRecord {
+    /// Output path, relative to the output directory.
+    path: PathBuf,
+    /// When fingerprinting is enabled, the pre-rewrite path this file was
+    /// emitted from, so templates can be updated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprinted_from: Option<PathBuf>,
+    /// The compression or codec used to produce this variant.
+    encoding: String,
+    /// Lowercase hex SHA-256 of the file contents.
+    sha256: String,
+    /// Size of the file on disk, in bytes.
+    size: u64,
 }
 
-pub fn add_extension(path: PathBuf, ext: impl AsRef<OsStr>) -> PathBuf {
-    let mut os_string: OsString = path.into();
-    os_string.push(".");
-    os_string.push(ext.as_ref());
-    os_string.into()
+/// Every variant produced from one source input.
+#[derive(serde::Serialize)]
+struct ManifestEntry {
+    /// Original input path, relative to the input directory.
+    source: PathBuf,
+    variants: Vec<VariantRecord>,
 }
 
-fn cfg_int<T>(name: &str, range: RangeInclusive<T>, default: T) -> T
-where
-    T: FromStr + Display + PartialEq + PartialOrd,
-    T::Err: Debug,
-{
-    let level: T = std::env::var(name)
-        .map(|v| {
-            v.parse()
-                .unwrap_or_else(|_| panic!("{name} must be a valid integer"))
-        })
-        .unwrap_or(default);
-    if !range.contains(&level) {
-        panic!(
-            "{name} must be between {} and {}, inclusive.",
-            range.start(),
-            range.end()
-        );
-    }
-    level
+/// Hash the just-written output `bytes`, optionally rewrite the file's name to
+/// embed a short fingerprint, and produce the manifest record describing it.
+fn finalize_variant(
+    args: &Arguments,
+    path: PathBuf,
+    encoding: &str,
+    bytes: &[u8],
+    variants: &mut Vec<VariantRecord>,
+) -> Result<(), Error> {
+    let sha256 = hex_digest(bytes);
+
+    let (final_path, fingerprinted_from) = if args.fingerprint {
+        let fingerprinted = fingerprint_path(&path, &sha256)?;
+        let original = path.strip_prefix(&args.outdir)?.to_path_buf();
+        std::fs::rename(&path, &fingerprinted)?;
+        (fingerprinted, Some(original))
+    } else {
+        (path, None)
+    };
+
+    variants.push(VariantRecord {
+        path: final_path.strip_prefix(&args.outdir)?.to_path_buf(),
+        fingerprinted_from,
+        encoding: encoding.to_owned(),
+        sha256,
+        size: bytes.len() as u64,
+    });
+    Ok(())
 }
 
-#[derive(Clone)]
-struct Config<'a> {
-    webp: WebPQualityConfig,
-    brotli: u32,
-    zstd: i32,
-    deflate: u32,
-    gzip: u32,
-    no_resize_images: bool,
-    no_compress_images: bool,
-    in_dir: &'a Path,
-    out_dir: &'a Path,
+/// Insert a short content fingerprint before the file's extension, e.g.
+/// `app.png` with digest `9f3c…` becomes `app.9f3c….png`.
+fn fingerprint_path(path: &Path, sha256: &str) -> Result<PathBuf, Error> {
+    let short = &sha256[..8];
+    gen_path(path, &format!(".{short}"))
 }
 
-impl<'a> Config<'a> {
-    fn new(
-        in_dir: &'a Path,
-        out_dir: &'a Path,
-        no_resize_images: bool,
-        no_compress_images: bool,
-    ) -> Self {
-        Self {
-            webp: Default::default(),
-            zstd: cfg_int(
-                "ZSTD_LEVEL",
-                zstd::compression_level_range(),
-                DEFAULT_ZSTD_LEVEL,
-            ),
-            brotli: cfg_int("BROTLI_LEVEL", 1..=11, DEFAULT_BROTLI_LEVEL),
-            deflate: cfg_int("DEFLATE_LEVEL", 1..=9, DEFAULT_DEFLATE_LEVEL),
-            gzip: cfg_int("GZIP_LEVEL", 1..=9, DEFAULT_GZIP_LEVEL),
-            no_resize_images,
-            no_compress_images,
-            in_dir,
-            out_dir,
-        }
+fn hex_digest(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let digest = Sha256::digest(bytes);
+    let mut out = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        out.push(HEX[(byte >> 4) as usize] as char);
+        out.push(HEX[(byte & 0x0f) as usize] as char);
     }
+    out
 }
 
-#[derive(Clone, Copy)]
-enum WebPQualityConfig {
-    Lossless,
-    Lossy(f32),
+fn write_manifest(out_dir: &Path, manifest: &[ManifestEntry]) -> Result<(), Error> {
+    let path = out_dir.join("manifest.json");
+    let file = create_file(path)?;
+    serde_json::to_writer_pretty(file, manifest)?;
+    Ok(())
 }
 
-impl Default for WebPQualityConfig {
-    fn default() -> Self {
-        if std::env::var("WEBP_LOSSLESS").is_ok_and(|v| v != "false" && v != "0") {
-            WebPQualityConfig::Lossless
-        } else if let Ok(requested_quality) = std::env::var("WEBP_QUALITY") {
-            let requested_quality: f32 = requested_quality
-                .parse()
-                .expect("WEBP_QUALITY must be a float between 0 and 100, inclusive.");
-            if !(0.0..=100.0).contains(&requested_quality) {
-                panic!("Expected WEBP_QUALITY to be a float between 0 and 100, inclusive.");
+/// Pack the rendered output tree into a single tar archive at `archive_path`,
+/// then emit whole-archive zstd/gzip/lz4 forms alongside it. Relative paths
+/// under the output directory are preserved. The per-file precompressed
+/// `.br/.gz/.zst/.zz` siblings are excluded unless `--archive-include-compressed`
+/// is set, since the whole-archive compression supersedes them. The gzip and
+/// zstd passes honor the same `GZIP_LEVEL`/`ZSTD_LEVEL` knobs as the rest of the
+/// tool.
+fn write_archive(args: &Arguments, config: &Config, archive_path: &Path) -> Result<(), Error> {
+    // Stream each variant straight into a file-backed tar so the whole archive
+    // never lives in memory — transcoded videos alone would make that a footgun.
+    let mut builder = tar::Builder::new(create_file(archive_path)?);
+
+    for entry in WalkDir::new(&args.outdir) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let relative = path.strip_prefix(&args.outdir)?;
+
+        if !args.archive_include_compressed {
+            if let Some(ext) = path.extension() {
+                if AssetKind::from_extension(ext) == AssetKind::Precompressed {
+                    continue;
+                }
             }
-            WebPQualityConfig::Lossy(requested_quality)
-        } else {
-            WebPQualityConfig::Lossy(DEFAULT_WEBP_COMPRESSION)
         }
+
+        builder
+            .append_path_with_name(path, relative)
+            .map_err(Error::Archive)?;
     }
+
+    builder.into_inner().map_err(Error::Archive)?;
+
+    // Compress the on-disk archive by streaming it through each compressor into
+    // its own output file, rather than holding full copies in memory.
+    let zst_path = add_extension(archive_path.to_path_buf(), "zst");
+    let mut zst = zstd::Encoder::new(create_file(&zst_path)?, config.zstd())?;
+    std::io::copy(&mut std::fs::File::open(archive_path)?, &mut zst)?;
+    zst.finish()?;
+
+    let gz_path = add_extension(archive_path.to_path_buf(), "gz");
+    let mut gz = GzEncoder::new(create_file(&gz_path)?, Compression::new(config.gzip()));
+    std::io::copy(&mut std::fs::File::open(archive_path)?, &mut gz)?;
+    gz.finish()?;
+
+    let lz4_path = add_extension(archive_path.to_path_buf(), "lz4");
+    let mut lz4 = FrameEncoder::new(create_file(&lz4_path)?);
+    std::io::copy(&mut std::fs::File::open(archive_path)?, &mut lz4)?;
+    lz4.finish()?;
+
+    Ok(())
 }
 
-impl WebPQualityConfig {
-    pub fn lossless(&self) -> bool {
-        match self {
-            Self::Lossless => true,
-            Self::Lossy(_) => false,
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
 
-    pub fn quality(&self) -> f32 {
-        match self {
-            Self::Lossless => 75.0,
-            Self::Lossy(v) => v.clamp(0.0, 100.0),
-        }
+    use super::*;
+
+    #[test]
+    fn hex_digest_matches_known_sha256() {
+        assert_eq!(
+            hex_digest(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
     }
-}
 
-#[derive(Debug, thiserror::Error)]
-pub enum Error {
-    #[error("I/O error: {0}")]
-    Io(#[from] IoError),
-    #[error("Directory walking error: {0}")]
-    Walkdir(#[from] WalkDirError),
-    #[error("Image coding error: {0}")]
-    Image(#[from] ImageError),
-    #[error("WebP Encoding error")]
-    WebP(WebPEncodingError),
-    #[error("Prefix stripping error")]
-    StripPrefixError(#[from] std::path::StripPrefixError),
-    #[error("Encountered a file with no extension")]
-    NoExtension,
-    #[error("Encountered a file with no name")]
-    NoFileName,
-    #[error("WebP does not support some dynamic image types: https://docs.rs/webp/0.2.6/src/webp/encoder.rs.html#29-45")]
-    UnimplementedWebPImageFormat,
-}
+    #[test]
+    fn fingerprint_path_inserts_a_short_hash() {
+        let digest = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let path = fingerprint_path(Path::new("out/app.png"), digest).unwrap();
+        assert_eq!(path, PathBuf::from("out/app.e3b0c442.png"));
+    }
 
-impl From<WebPEncodingError> for Error {
-    fn from(value: WebPEncodingError) -> Self {
-        Self::WebP(value)
+    #[test]
+    fn image_variant_covers_only_emitted_formats() {
+        assert!(image_variant_covers(OsStr::new("png")));
+        assert!(image_variant_covers(OsStr::new("webp")));
+        assert!(image_variant_covers(OsStr::new("jpeg")));
+        // `jpg` re-renders to `jpeg`, so the original name is not covered.
+        assert!(!image_variant_covers(OsStr::new("jpg")));
+        assert!(!image_variant_covers(OsStr::new("bmp")));
     }
 }